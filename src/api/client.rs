@@ -1,38 +1,207 @@
 use futures_util::StreamExt;
 use plugin_interfaces::{log_info, log_warn, PluginInstanceContext, StreamError};
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
-use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 
-use super::types::{ChatCompletionChunk, Message};
+use super::provider::AiProvider;
+use super::retry::{is_retryable_status, retry_after, RetryPolicy};
+use super::sse::SseDecoder;
+use super::types::{DeltaKind, GenOptions, Message};
 
 #[derive(Clone)]
 pub struct ApiClient {
     client: Arc<Mutex<Option<reqwest::Client>>>,
-    api_key: String,
-    api_url: String,
+    provider: Arc<dyn AiProvider>,
+    retry_policy: RetryPolicy,
 }
 
 impl ApiClient {
-    pub fn new(api_key: String, api_url: String) -> Self {
+    pub fn new(provider: Arc<dyn AiProvider>) -> Self {
         Self {
             client: Arc::new(Mutex::new(None)),
-            api_key,
-            api_url,
+            provider,
+            retry_policy: RetryPolicy::default(),
         }
     }
 
-    pub async fn initialize(&self) {
-        let client = reqwest::Client::new();
+    /// 构建底层 HTTP 客户端，可选代理、整体请求超时与单独的连接超时
+    ///
+    /// `timeout_secs` 控制整个请求（含读取响应体）的超时，`connect_timeout_secs`
+    /// 只控制 TCP/TLS 建连阶段，避免一个建连缓慢的代理把读超时也顶满。
+    pub async fn initialize(
+        &self,
+        proxy: Option<String>,
+        timeout_secs: Option<u64>,
+        connect_timeout_secs: Option<u64>,
+    ) {
+        let mut builder = reqwest::Client::builder();
+
+        if let Some(proxy_url) = proxy.filter(|p| !p.trim().is_empty()) {
+            match reqwest::Proxy::all(&proxy_url) {
+                Ok(proxy) => builder = builder.proxy(proxy),
+                Err(e) => log_warn!("Invalid proxy url {}: {}", proxy_url, e),
+            }
+        }
+
+        if let Some(secs) = timeout_secs {
+            builder = builder.timeout(Duration::from_secs(secs));
+        }
+
+        if let Some(secs) = connect_timeout_secs {
+            builder = builder.connect_timeout(Duration::from_secs(secs));
+        }
+
+        let client = match builder.build() {
+            Ok(client) => client,
+            Err(e) => {
+                log_warn!(
+                    "Failed to build HTTP client with custom options: {}, falling back to default",
+                    e
+                );
+                reqwest::Client::new()
+            }
+        };
+
         let mut client_guard = self.client.lock().await;
         *client_guard = Some(client);
         log_info!("HTTP client initialized");
     }
 
+    /// 发送一次请求，对连接错误和 429/5xx 响应做指数退避重试
+    ///
+    /// 重试只发生在拿到成功的响应之前；一旦拿到成功响应（即将开始流式转发），
+    /// 调用方不会再重试，避免向用户重复输出已经流出的部分内容。
+    async fn send_with_retry(
+        &self,
+        client: &reqwest::Client,
+        messages: &[Message],
+        opts: &GenOptions,
+    ) -> Result<reqwest::Response, Box<dyn std::error::Error + Send + Sync>> {
+        let mut attempt = 0u32;
+
+        loop {
+            attempt += 1;
+            let result = self
+                .provider
+                .build_request(client, messages, opts)
+                .send()
+                .await;
+
+            match result {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    if is_retryable_status(status) && attempt < self.retry_policy.max_attempts {
+                        let wait = retry_after(response.headers())
+                            .unwrap_or_else(|| self.retry_policy.delay_for(attempt));
+                        log_warn!(
+                            "Request failed with status {}, retrying in {:?} (attempt {}/{})",
+                            status,
+                            wait,
+                            attempt,
+                            self.retry_policy.max_attempts
+                        );
+                        tokio::time::sleep(wait).await;
+                        continue;
+                    }
+
+                    let error_text = response.text().await?;
+                    return Err(format!("API 请求失败: {}", error_text).into());
+                }
+                Err(e) => {
+                    if e.is_connect() && attempt < self.retry_policy.max_attempts {
+                        let wait = self.retry_policy.delay_for(attempt);
+                        log_warn!(
+                            "Connection error: {}, retrying in {:?} (attempt {}/{})",
+                            e,
+                            wait,
+                            attempt,
+                            self.retry_policy.max_attempts
+                        );
+                        tokio::time::sleep(wait).await;
+                        continue;
+                    }
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+
+    /// 发送一次流式请求，把每个文本增量通过 `on_delta` 转发出去
+    ///
+    /// 与 [`ApiClient::send_streaming_request`] 的区别是这里完全不依赖
+    /// `PluginInstanceContext`/`StreamError`，只认 messages/opts 和一个
+    /// 纯回调，所以既能被 `handle_message` 路径驱动，也能被内嵌 HTTP
+    /// 网关驱动。
+    pub async fn send_streaming_request_raw<F>(
+        &self,
+        messages: Vec<Message>,
+        opts: &GenOptions,
+        mut on_delta: F,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>
+    where
+        F: FnMut(&str, DeltaKind) -> Result<(), Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let client = {
+            let client_guard = self.client.lock().await;
+            client_guard.clone()
+        };
+        let client = client.ok_or("HTTP 客户端未初始化")?;
+
+        log_info!(
+            "Sending streaming request to {} provider with {} messages",
+            self.provider.name(),
+            messages.len()
+        );
+
+        // 发送请求：仅在还没有任何内容流出之前重试，避免重放已输出一半的回复
+        let response = self.send_with_retry(&client, &messages, opts).await?;
+
+        let mut stream = response.bytes_stream();
+        let mut decoder = SseDecoder::new();
+        let mut has_content = false;
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+
+            for data in decoder.push(&chunk) {
+                // 检查是否为结束标记
+                if data == "[DONE]" {
+                    log_info!("Stream completed");
+                    return Ok(true);
+                }
+
+                match self.provider.parse_chunk(&data) {
+                    Ok(Some(delta)) => {
+                        // 推理模型的思维链增量与最终回答增量分别打标签转发
+                        let pieces = [
+                            (delta.reasoning_content, DeltaKind::Reasoning),
+                            (delta.content, DeltaKind::Answer),
+                        ];
+
+                        for (text, kind) in pieces {
+                            let Some(text) = text else { continue };
+                            has_content = true;
+                            on_delta(&text, kind)?;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        log_warn!("Failed to parse chunk: {} - Data: {}", e, data);
+                    }
+                }
+            }
+        }
+
+        Ok(has_content)
+    }
+
     pub async fn send_streaming_request<F1, F2, F3>(
         &self,
         messages: Vec<Message>,
+        opts: &GenOptions,
         plugin_ctx: &PluginInstanceContext,
         send_message_stream_start: F1,
         send_message_stream: F2,
@@ -40,7 +209,7 @@ impl ApiClient {
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
     where
         F1: Fn(&PluginInstanceContext) -> Result<String, Box<dyn std::error::Error>>,
-        F2: Fn(&str, &str, bool, &PluginInstanceContext) -> Result<(), StreamError>,
+        F2: Fn(&str, &str, DeltaKind, bool, &PluginInstanceContext) -> Result<(), StreamError>,
         F3: Fn(
             &str,
             bool,
@@ -48,125 +217,60 @@ impl ApiClient {
             &PluginInstanceContext,
         ) -> Result<(), Box<dyn std::error::Error>>,
     {
-        if self.api_key.trim().is_empty() {
-            return Err("API Key 未设置".into());
-        }
-
-        // 获取客户端
-        let client = {
-            let client_guard = self.client.lock().await;
-            client_guard.clone()
-        };
-
-        let client = client.ok_or("HTTP 客户端未初始化")?;
-
-        // 构建请求头
-        let mut headers = HeaderMap::new();
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", self.api_key))?,
-        );
-
-        // 构建请求体
-        let request_body = json!({
-            "model": "deepseek-chat",
-            "messages": messages,
-            "stream": true
-        });
-
-        log_info!(
-            "Sending streaming request to DeepSeek API with {} messages",
-            messages.len()
-        );
-
-        // 发送请求
-        let response = client
-            .post(&self.api_url)
-            .headers(headers)
-            .json(&request_body)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(format!("API 请求失败: {}", error_text).into());
-        }
-
         // 开始流式传输
         let stream_id = match send_message_stream_start(plugin_ctx) {
             Ok(id) => id,
             Err(e) => return Err(format!("启动流式传输失败: {}", e).into()),
         };
 
-        let mut stream = response.bytes_stream();
-        let mut has_content = false;
+        // `AtomicBool` (not `Cell`) so this stays `Sync`: the closure below is held
+        // across `.await` points inside `send_streaming_request_raw`, and that
+        // future is in turn driven via `runtime.spawn(...)` by callers, which
+        // requires `Future + Send`.
+        let cancelled = AtomicBool::new(false);
 
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            let chunk_str = String::from_utf8_lossy(&chunk);
-
-            // 处理 SSE 格式的数据
-            for line in chunk_str.split("\n\n") {
-                if line.starts_with("data: ") {
-                    let data = &line[6..];
-
-                    // 检查是否为结束标记
-                    if data == "[DONE]" {
-                        log_info!("Stream completed");
-                        let _ = send_message_stream_end(&stream_id, true, None, plugin_ctx);
-                        return Ok(());
+        let result = self
+            .send_streaming_request_raw(messages, opts, |text, kind| {
+                match send_message_stream(&stream_id, text, kind, false, plugin_ctx) {
+                    Ok(()) => Ok(()),
+                    Err(StreamError::StreamCancelled) => {
+                        cancelled.store(true, Ordering::Relaxed);
+                        Err("stream cancelled by user".into())
                     }
-
-                    // 解析 JSON
-                    match serde_json::from_str::<ChatCompletionChunk>(data) {
-                        Ok(chunk_data) => {
-                            for choice in chunk_data.choices {
-                                if let Some(content) = choice.delta.content {
-                                    has_content = true;
-                                    if let Err(e) =
-                                        send_message_stream(&stream_id, &content, false, plugin_ctx)
-                                    {
-                                        match e {
-                                            StreamError::StreamCancelled => {
-                                                log_info!(
-                                                    "Stream {} was cancelled by user, stopping gracefully...",
-                                                    stream_id
-                                                );
-                                                return Ok(()); // 用户取消，直接返回，不发送错误消息
-                                            }
-                                            _ => {
-                                                log_warn!(
-                                                    "Failed to send background stream chunk: {}",
-                                                    e
-                                                );
-                                                let _ = send_message_stream_end(
-                                                    &stream_id,
-                                                    false,
-                                                    Some(&format!("Error: {}", e)),
-                                                    &plugin_ctx,
-                                                );
-                                                return Err(e.into());
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            log_warn!("Failed to parse chunk: {} - Data: {}", e, data);
-                        }
+                    Err(e) => {
+                        log_warn!("Failed to send background stream chunk: {}", e);
+                        Err(format!("Error: {}", e).into())
                     }
                 }
-            }
-        }
+            })
+            .await;
 
-        if has_content {
-            let _ = send_message_stream_end(&stream_id, true, None, plugin_ctx);
-        } else {
-            let _ = send_message_stream_end(&stream_id, false, Some("未收到有效回复"), plugin_ctx);
+        if cancelled.load(Ordering::Relaxed) {
+            log_info!(
+                "Stream {} was cancelled by user, stopping gracefully...",
+                stream_id
+            );
+            return Ok(()); // 用户取消，直接返回，不发送错误消息
         }
 
-        Ok(())
+        match result {
+            Ok(has_content) => {
+                if has_content {
+                    let _ = send_message_stream_end(&stream_id, true, None, plugin_ctx);
+                } else {
+                    let _ = send_message_stream_end(
+                        &stream_id,
+                        false,
+                        Some("未收到有效回复"),
+                        plugin_ctx,
+                    );
+                }
+                Ok(())
+            }
+            Err(e) => {
+                let _ = send_message_stream_end(&stream_id, false, Some(&e.to_string()), plugin_ctx);
+                Err(e)
+            }
+        }
     }
 }
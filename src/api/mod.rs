@@ -0,0 +1,11 @@
+mod client;
+mod provider;
+mod providers;
+mod retry;
+mod sse;
+mod types;
+
+pub use client::ApiClient;
+pub use provider::{AiProvider, ParseError};
+pub use providers::create_provider;
+pub use types::{ChatCompletionChunk, Choice, Delta, DeltaKind, GenOptions, Message};
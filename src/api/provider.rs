@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+use reqwest::RequestBuilder;
+
+use super::types::{Delta, GenOptions, Message};
+
+/// 解析供应商 SSE 数据块时可能出现的错误
+#[derive(Debug)]
+pub enum ParseError {
+    Json(serde_json::Error),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Json(e) => write!(f, "解析响应失败: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<serde_json::Error> for ParseError {
+    fn from(e: serde_json::Error) -> Self {
+        ParseError::Json(e)
+    }
+}
+
+/// 统一的 AI 服务提供商接口
+///
+/// 屏蔽不同供应商在鉴权方式、请求体结构和流式响应格式上的差异，
+/// 使 `ApiClient` 可以用同一套流程驱动任意实现。新增供应商只需
+/// 新增一个实现并在 [`super::providers::create_provider`] 中注册。
+#[async_trait]
+pub trait AiProvider: Send + Sync {
+    /// 供应商名称，用于日志输出
+    fn name(&self) -> &str;
+
+    /// 基于消息列表和生成参数构建一次请求
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        messages: &[Message],
+        opts: &GenOptions,
+    ) -> RequestBuilder;
+
+    /// 解析一条已组装完整的 SSE `data:` 内容，返回增量
+    ///
+    /// 对于不携带内容的事件（如心跳、元数据事件）返回 `Ok(None)`。
+    fn parse_chunk(&self, data: &str) -> Result<Option<Delta>, ParseError>;
+}
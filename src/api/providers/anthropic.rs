@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::api::provider::{AiProvider, ParseError};
+use crate::api::types::{Delta, GenOptions, Message};
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Anthropic Messages API：`x-api-key` 鉴权，系统提示独立于消息列表传递
+pub struct AnthropicProvider {
+    api_key: String,
+    api_url: String,
+    model: String,
+}
+
+impl AnthropicProvider {
+    pub fn new(api_key: String, api_url: String, model: String) -> Self {
+        Self {
+            api_key,
+            api_url,
+            model,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct AnthropicStreamDelta {
+    #[serde(rename = "type")]
+    kind: String,
+    text: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AnthropicEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    delta: Option<AnthropicStreamDelta>,
+}
+
+#[async_trait]
+impl AiProvider for AnthropicProvider {
+    fn name(&self) -> &str {
+        "anthropic"
+    }
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        messages: &[Message],
+        opts: &GenOptions,
+    ) -> RequestBuilder {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if let Ok(value) = HeaderValue::from_str(&self.api_key) {
+            headers.insert("x-api-key", value);
+        }
+        headers.insert("anthropic-version", HeaderValue::from_static(ANTHROPIC_VERSION));
+
+        // Anthropic 不接受消息数组里的 system 角色，需要单独提到顶层字段
+        let system = messages
+            .iter()
+            .find(|m| m.role == "system")
+            .map(|m| m.content.clone());
+        let rest: Vec<&Message> = messages.iter().filter(|m| m.role != "system").collect();
+
+        let mut body = json!({
+            "model": self.model,
+            "messages": rest,
+            "max_tokens": opts.max_tokens.unwrap_or(4096),
+            "stream": opts.stream,
+        });
+        if let Some(system) = system {
+            body["system"] = json!(system);
+        }
+        if let Some(temperature) = opts.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = opts.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(stop) = &opts.stop {
+            // Anthropic 用 stop_sequences 而非 OpenAI 风格的 stop 字段名
+            body["stop_sequences"] = json!(stop);
+        }
+
+        client.post(&self.api_url).headers(headers).json(&body)
+    }
+
+    fn parse_chunk(&self, data: &str) -> Result<Option<Delta>, ParseError> {
+        let event: AnthropicEvent = serde_json::from_str(data)?;
+        if event.kind != "content_block_delta" {
+            return Ok(None);
+        }
+
+        let text = event
+            .delta
+            .filter(|d| d.kind == "text_delta")
+            .and_then(|d| d.text);
+
+        Ok(text.map(|content| Delta {
+            content: Some(content),
+            reasoning_content: None,
+        }))
+    }
+}
@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+use reqwest::RequestBuilder;
+
+use crate::api::provider::{AiProvider, ParseError};
+use crate::api::types::{Delta, GenOptions, Message};
+
+use super::openai_compatible::OpenAiCompatibleProvider;
+
+/// DeepSeek 官方 API：协议上就是一套 OpenAI 兼容的 `chat/completions`，
+/// 这里只委托给 [`OpenAiCompatibleProvider`] 并把 `name()` 报成 "deepseek"
+pub struct DeepSeekProvider(OpenAiCompatibleProvider);
+
+impl DeepSeekProvider {
+    pub fn new(api_key: String, api_url: String, model: String) -> Self {
+        Self(OpenAiCompatibleProvider::with_name(
+            api_key, api_url, model, "deepseek",
+        ))
+    }
+}
+
+#[async_trait]
+impl AiProvider for DeepSeekProvider {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        messages: &[Message],
+        opts: &GenOptions,
+    ) -> RequestBuilder {
+        self.0.build_request(client, messages, opts)
+    }
+
+    fn parse_chunk(&self, data: &str) -> Result<Option<Delta>, ParseError> {
+        self.0.parse_chunk(data)
+    }
+}
@@ -0,0 +1,26 @@
+mod anthropic;
+mod deepseek;
+mod openai_compatible;
+
+use std::sync::Arc;
+
+use super::provider::AiProvider;
+use crate::config::ProviderKind;
+
+/// 根据配置中的 provider 类型创建对应的 [`AiProvider`] 实现
+pub fn create_provider(
+    kind: &ProviderKind,
+    api_key: String,
+    api_url: String,
+    model: String,
+) -> Arc<dyn AiProvider> {
+    match kind {
+        ProviderKind::DeepSeek => Arc::new(deepseek::DeepSeekProvider::new(api_key, api_url, model)),
+        ProviderKind::OpenAiCompatible => {
+            Arc::new(openai_compatible::OpenAiCompatibleProvider::new(
+                api_key, api_url, model,
+            ))
+        }
+        ProviderKind::Anthropic => Arc::new(anthropic::AnthropicProvider::new(api_key, api_url, model)),
+    }
+}
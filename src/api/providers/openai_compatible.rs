@@ -0,0 +1,80 @@
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::RequestBuilder;
+use serde_json::json;
+
+use crate::api::provider::{AiProvider, ParseError};
+use crate::api::types::{ChatCompletionChunk, Delta, GenOptions, Message};
+
+/// 任意 OpenAI 兼容网关（OpenAI、OpenRouter、DeepSeek、本地 `/v1/chat/completions`
+/// 服务等）共用的实现：`Bearer` 鉴权 + OpenAI 风格的 `chat/completions` 请求/响应
+/// 格式。不同供应商只是把 `name` 报成不同的字符串，[`super::deepseek::DeepSeekProvider`]
+/// 就是委托到这里的一个薄包装，避免两份一模一样的 `build_request`/`parse_chunk`
+/// 悄悄分叉。
+pub struct OpenAiCompatibleProvider {
+    api_key: String,
+    api_url: String,
+    model: String,
+    name: &'static str,
+}
+
+impl OpenAiCompatibleProvider {
+    pub fn new(api_key: String, api_url: String, model: String) -> Self {
+        Self::with_name(api_key, api_url, model, "openai-compatible")
+    }
+
+    /// 给这套 OpenAI 兼容实现起一个不同的 `name()`，供协议完全相同、只是
+    /// 供应商标识不同的实现委托
+    pub(crate) fn with_name(
+        api_key: String,
+        api_url: String,
+        model: String,
+        name: &'static str,
+    ) -> Self {
+        Self {
+            api_key,
+            api_url,
+            model,
+            name,
+        }
+    }
+}
+
+#[async_trait]
+impl AiProvider for OpenAiCompatibleProvider {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        messages: &[Message],
+        opts: &GenOptions,
+    ) -> RequestBuilder {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if let Ok(value) = HeaderValue::from_str(&format!("Bearer {}", self.api_key)) {
+            headers.insert(AUTHORIZATION, value);
+        }
+
+        let mut body = json!({
+            "model": self.model,
+            "messages": messages,
+            "stream": opts.stream,
+        });
+        opts.merge_into_openai_body(&mut body);
+
+        client.post(&self.api_url).headers(headers).json(&body)
+    }
+
+    fn parse_chunk(&self, data: &str) -> Result<Option<Delta>, ParseError> {
+        let chunk: ChatCompletionChunk = serde_json::from_str(data)?;
+        Ok(chunk
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.delta)
+            .filter(|delta| !delta.is_empty()))
+    }
+}
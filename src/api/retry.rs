@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+/// 指数退避重试参数：5 次尝试、基础延迟 500ms、倍增上限 30s
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 `attempt` 次（从 1 开始）失败后，在下一次尝试前应等待的时长
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let multiplier = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(multiplier).min(self.max_delay);
+        with_jitter(delay)
+    }
+}
+
+/// 给退避时长加上 50%~100% 的随机抖动，避免多个客户端同时重试
+///
+/// 没有引入 `rand` 依赖，用当前时间的纳秒位做一个轻量的伪随机源即可。
+fn with_jitter(delay: Duration) -> Duration {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let factor = 0.5 + (nanos % 1000) as f64 / 2000.0;
+    Duration::from_secs_f64(delay.as_secs_f64() * factor)
+}
+
+/// HTTP 状态码是否值得重试（429 限流、5xx 服务端错误）
+pub fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// 解析 `Retry-After` 响应头（目前只支持以秒为单位的数值形式）
+pub fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `with_jitter` 把延迟乘上一个 0.5~1.0 之间的随机系数，所以这里只断言
+    // delay_for 落在 [base * 0.5, base * 1.0] 这个区间内，而不是一个精确值
+    fn assert_within_jittered_range(actual: Duration, undampened: Duration) {
+        let lower = undampened.mul_f64(0.5);
+        assert!(
+            actual >= lower && actual <= undampened,
+            "{:?} not within [{:?}, {:?}]",
+            actual,
+            lower,
+            undampened
+        );
+    }
+
+    #[test]
+    fn delay_grows_exponentially_with_attempt() {
+        let policy = RetryPolicy::default();
+
+        assert_within_jittered_range(policy.delay_for(1), policy.base_delay);
+        assert_within_jittered_range(policy.delay_for(2), policy.base_delay * 2);
+        assert_within_jittered_range(policy.delay_for(3), policy.base_delay * 4);
+        assert_within_jittered_range(policy.delay_for(4), policy.base_delay * 8);
+    }
+
+    #[test]
+    fn delay_is_capped_at_max_delay() {
+        let policy = RetryPolicy::default();
+
+        // attempt 7 的未抖动延迟是 500ms * 2^6 = 32s，已经超过 30s 上限
+        assert_within_jittered_range(policy.delay_for(7), policy.max_delay);
+        // attempt 再大也不应该超过上限本身
+        assert_within_jittered_range(policy.delay_for(20), policy.max_delay);
+    }
+
+    #[test]
+    fn delay_never_exceeds_max_delay_for_any_attempt() {
+        let policy = RetryPolicy::default();
+
+        for attempt in 1..=20u32 {
+            assert!(policy.delay_for(attempt) <= policy.max_delay);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+/// 增量 SSE（Server-Sent Events）解码器
+///
+/// `bytes_stream()` 产生的网络分片边界和事件/行边界无关，可能把一个 UTF-8
+/// 多字节序列或一条 `data:` 行切成两半。该解码器只在遇到完整的 `\n` 结尾的
+/// 行时才解码为字符串，并把跨分片的剩余字节留到下一次 `push` 继续拼接，
+/// 从而保证同一事件的多条 `data:` 行会被合并成一个完整事件再交给调用方。
+#[derive(Default)]
+pub struct SseDecoder {
+    buf: Vec<u8>,
+    pending_data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 喂入新到达的字节，返回本次新组装完成的事件（已合并多条 `data:` 行）
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buf.extend_from_slice(bytes);
+
+        let mut events = Vec::new();
+
+        while let Some(newline_pos) = self.buf.iter().position(|&b| b == b'\n') {
+            let line_bytes: Vec<u8> = self.buf.drain(..=newline_pos).collect();
+            let mut line = String::from_utf8_lossy(&line_bytes[..line_bytes.len() - 1]).into_owned();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+
+            if line.is_empty() {
+                // 空行代表一个事件结束
+                if !self.pending_data.is_empty() {
+                    events.push(self.pending_data.join("\n"));
+                    self.pending_data.clear();
+                }
+                continue;
+            }
+
+            if let Some(rest) = line.strip_prefix("data:") {
+                // SSE 规范：冒号后至多一个空格是分隔符，不属于内容
+                let rest = rest.strip_prefix(' ').unwrap_or(rest);
+                self.pending_data.push(rest.to_string());
+            }
+            // 其余字段（event:/id:/retry: 等）目前不需要，忽略
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_push_with_complete_event() {
+        let mut decoder = SseDecoder::new();
+        let events = decoder.push(b"data: hello\n\n");
+        assert_eq!(events, vec!["hello".to_string()]);
+    }
+
+    #[test]
+    fn event_split_across_two_pushes() {
+        let mut decoder = SseDecoder::new();
+        assert_eq!(decoder.push(b"data: hel"), Vec::<String>::new());
+        assert_eq!(decoder.push(b"lo\n\n"), vec!["hello".to_string()]);
+    }
+
+    #[test]
+    fn multibyte_utf8_sequence_split_across_pushes() {
+        // “你好” 的 UTF-8 编码里，第一个字节落在第一次 push，其余字节落在
+        // 第二次 push——在凑齐完整的一行之前不应该尝试解码
+        let mut decoder = SseDecoder::new();
+        let full = "data: 你好\n\n".as_bytes().to_vec();
+        let (first, rest) = full.split_at(7);
+        assert_eq!(decoder.push(first), Vec::<String>::new());
+        assert_eq!(decoder.push(rest), vec!["你好".to_string()]);
+    }
+
+    #[test]
+    fn newline_split_from_its_own_data() {
+        // 换行符本身和它所属的数据分属两次 push
+        let mut decoder = SseDecoder::new();
+        assert_eq!(decoder.push(b"data: hello"), Vec::<String>::new());
+        assert_eq!(decoder.push(b"\n\n"), vec!["hello".to_string()]);
+    }
+
+    #[test]
+    fn multiple_data_lines_join_with_newline_into_one_event() {
+        let mut decoder = SseDecoder::new();
+        let events = decoder.push(b"data: line one\ndata: line two\n\n");
+        assert_eq!(events, vec!["line one\nline two".to_string()]);
+    }
+
+    #[test]
+    fn crlf_line_endings_are_stripped() {
+        let mut decoder = SseDecoder::new();
+        let events = decoder.push(b"data: hello\r\n\r\n");
+        assert_eq!(events, vec!["hello".to_string()]);
+    }
+
+    #[test]
+    fn non_data_fields_are_ignored() {
+        let mut decoder = SseDecoder::new();
+        let events = decoder.push(b"event: message\nid: 1\ndata: hello\n\n");
+        assert_eq!(events, vec!["hello".to_string()]);
+    }
+
+    #[test]
+    fn single_space_after_colon_is_stripped_but_not_further_leading_spaces() {
+        let mut decoder = SseDecoder::new();
+        let events = decoder.push(b"data:  hello\n\n");
+        assert_eq!(events, vec![" hello".to_string()]);
+    }
+
+    #[test]
+    fn multiple_events_in_one_push() {
+        let mut decoder = SseDecoder::new();
+        let events = decoder.push(b"data: one\n\ndata: two\n\n");
+        assert_eq!(events, vec!["one".to_string(), "two".to_string()]);
+    }
+
+    #[test]
+    fn incomplete_trailing_event_waits_for_blank_line() {
+        let mut decoder = SseDecoder::new();
+        assert_eq!(decoder.push(b"data: hello\n"), Vec::<String>::new());
+        assert_eq!(decoder.push(b"data: world\n\n"), vec!["hello\nworld".to_string()]);
+    }
+}
@@ -1,15 +1,31 @@
 use serde::{Deserialize, Serialize};
 
-/// DeepSeek API 响应中的选择项
+/// DeepSeek / OpenAI 兼容 API 响应中的选择项
 #[derive(Deserialize, Debug)]
 pub struct Choice {
     pub delta: Delta,
 }
 
 /// 消息增量（用于流式响应）
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct Delta {
     pub content: Option<String>,
+    /// `deepseek-reasoner` 等推理模型单独下发的思维链增量
+    #[serde(default)]
+    pub reasoning_content: Option<String>,
+}
+
+impl Delta {
+    pub fn is_empty(&self) -> bool {
+        self.content.is_none() && self.reasoning_content.is_none()
+    }
+}
+
+/// 区分一条增量是最终回答还是模型的思维链（reasoning）内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeltaKind {
+    Answer,
+    Reasoning,
 }
 
 /// 流式响应数据块
@@ -46,3 +62,43 @@ impl Message {
         Self::new("system", content)
     }
 }
+
+/// 生成参数，驱动 `AiProvider::build_request` 如何组装请求体
+#[derive(Clone, Debug)]
+pub struct GenOptions {
+    pub stream: bool,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub top_p: Option<f32>,
+    pub stop: Option<Vec<String>>,
+}
+
+impl Default for GenOptions {
+    fn default() -> Self {
+        Self {
+            stream: true,
+            temperature: None,
+            max_tokens: None,
+            top_p: None,
+            stop: None,
+        }
+    }
+}
+
+impl GenOptions {
+    /// 把已设置的生成参数合并进 OpenAI 风格的请求体（DeepSeek、OpenAI 兼容网关共用）
+    pub fn merge_into_openai_body(&self, body: &mut serde_json::Value) {
+        if let Some(temperature) = self.temperature {
+            body["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            body["max_tokens"] = serde_json::json!(max_tokens);
+        }
+        if let Some(top_p) = self.top_p {
+            body["top_p"] = serde_json::json!(top_p);
+        }
+        if let Some(stop) = &self.stop {
+            body["stop"] = serde_json::json!(stop);
+        }
+    }
+}
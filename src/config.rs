@@ -2,18 +2,186 @@ use plugin_interfaces::{log_error, log_info, log_warn};
 use serde::{Deserialize, Serialize};
 use std::{fs, path::Path};
 
-/// 用户配置结构
+/// 支持的 AI 服务提供商类型，决定使用哪个 [`crate::api::AiProvider`] 实现
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    DeepSeek,
+    OpenAiCompatible,
+    Anthropic,
+}
+
+impl Default for ProviderKind {
+    fn default() -> Self {
+        ProviderKind::DeepSeek
+    }
+}
+
+impl ProviderKind {
+    /// 解析 UI 文本框里的供应商名称，无法识别时回退到 DeepSeek
+    pub fn parse(s: &str) -> Self {
+        match s.trim().to_lowercase().as_str() {
+            "openai" | "openai_compatible" | "openai-compatible" => ProviderKind::OpenAiCompatible,
+            "anthropic" => ProviderKind::Anthropic,
+            _ => ProviderKind::DeepSeek,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderKind::DeepSeek => "deepseek",
+            ProviderKind::OpenAiCompatible => "openai_compatible",
+            ProviderKind::Anthropic => "anthropic",
+        }
+    }
+
+    /// 该供应商典型上下文窗口对应的默认历史 token 预算
+    ///
+    /// 用作 `extract_within_token_budget` 的默认输入，未来如果供应商支持
+    /// 自定义上下文窗口长度，可以被用户配置覆盖。
+    pub fn default_token_budget(&self) -> usize {
+        match self {
+            ProviderKind::DeepSeek => 32_000,
+            ProviderKind::OpenAiCompatible => 8_000,
+            ProviderKind::Anthropic => 100_000,
+        }
+    }
+}
+
+/// 一个可复用的系统提示词预设，比如"简洁程序员"或"翻译官"
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct UserConfig {
+pub struct RolePreset {
+    pub name: String,
+    pub system_prompt: String,
+}
+
+/// 档案的后端类型：调用远程 API，还是由插件自己拉起一个本地推理进程
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendMode {
+    RemoteApi,
+    LocalSidecar,
+}
+
+impl Default for BackendMode {
+    fn default() -> Self {
+        BackendMode::RemoteApi
+    }
+}
+
+impl BackendMode {
+    /// 解析 UI 文本框里的后端类型，无法识别时回退到远程 API
+    pub fn parse(s: &str) -> Self {
+        match s.trim().to_lowercase().as_str() {
+            "local" | "local_sidecar" | "sidecar" => BackendMode::LocalSidecar,
+            _ => BackendMode::RemoteApi,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackendMode::RemoteApi => "remote",
+            BackendMode::LocalSidecar => "local_sidecar",
+        }
+    }
+}
+
+/// 本地推理进程（如 llama.cpp server、Ollama）的启动参数
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SidecarConfig {
+    /// 可执行文件路径
+    pub binary_path: String,
+    /// 模型文件路径
+    pub model_path: String,
+    /// 监听端口；就绪后插件会把档案的 `api_url` 指向这个端口
+    pub port: u16,
+    /// 传给子进程的额外参数，按空白分隔
+    pub extra_args: String,
+}
+
+/// 一个具名的机器人档案：供应商、接入点与模型的一整套搭配
+///
+/// 因为所有供应商都通过 OpenAI 风格的 `/chat/completions` 接口（或各自的
+/// 等价接口）收发消息，同一个插件只要切换 profile 就能在 DeepSeek、OpenAI、
+/// OpenRouter 或任意兼容网关之间来回切换，而不用重新填写一遍配置。
+///
+/// 档案也可以把 `backend` 设成 [`BackendMode::LocalSidecar`]，改为由插件
+/// 自己拉起一个本地 OpenAI 兼容推理进程，该进程就绪后插件发请求时使用的
+/// 地址会改为 `http://127.0.0.1:<port>/v1/chat/completions`（只在内存里，
+/// 档案保存的 `api_url` 不变），不需要云端 API Key。
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BotProfile {
+    pub name: String,
+    #[serde(default)]
+    pub provider: ProviderKind,
     pub api_key: Option<String>,
     pub api_url: Option<String>,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub backend: BackendMode,
+    pub sidecar: Option<SidecarConfig>,
 }
 
-impl Default for UserConfig {
-    fn default() -> Self {
+impl BotProfile {
+    /// 以给定名称创建一个指向默认 DeepSeek 接入点的空白档案
+    pub fn new(name: &str) -> Self {
         Self {
+            name: name.to_string(),
+            provider: ProviderKind::DeepSeek,
             api_key: None,
             api_url: Some("https://api.deepseek.com/v1/chat/completions".to_string()),
+            model: Some("deepseek-chat".to_string()),
+            temperature: None,
+            max_tokens: None,
+            backend: BackendMode::RemoteApi,
+            sidecar: None,
+        }
+    }
+}
+
+/// 用户配置结构
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UserConfig {
+    /// 所有已保存的机器人档案，至少包含一个
+    #[serde(default)]
+    pub profiles: Vec<BotProfile>,
+    /// 当前激活的档案名称，对应 `profiles` 中的某一项
+    pub active_profile: Option<String>,
+    pub top_p: Option<f32>,
+    /// 逗号分隔的停止序列，解析后作为 `GenOptions.stop` 传给供应商
+    pub stop_sequences: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<RolePreset>,
+    pub active_role: Option<String>,
+    pub proxy: Option<String>,
+    /// 整个请求（含读取响应体）的超时时间
+    pub request_timeout_secs: Option<u64>,
+    /// 仅 TCP/TLS 连接阶段的超时时间，独立于 `request_timeout_secs`
+    /// 控制的整体超时，避免建连慢的代理把读超时也顶满
+    pub connect_timeout_secs: Option<u64>,
+    /// 本地 OpenAI 兼容网关监听的端口，未设置则不启动
+    pub gateway_port: Option<u16>,
+    /// Arena 模式下参与对比的档案名，逗号分隔；未设置则不启用 Arena 模式
+    pub arena_profiles: Option<String>,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        let default_profile = BotProfile::new("default");
+        Self {
+            active_profile: Some(default_profile.name.clone()),
+            profiles: vec![default_profile],
+            top_p: None,
+            stop_sequences: None,
+            roles: Vec::new(),
+            active_role: None,
+            proxy: None,
+            request_timeout_secs: None,
+            connect_timeout_secs: None,
+            gateway_port: None,
+            arena_profiles: None,
         }
     }
 }
@@ -70,10 +238,10 @@ impl ConfigManager {
     }
 
     /// 保存用户配置到config.toml文件
-    pub fn save_user_config(&self, api_key: &str, api_url: &str) {
+    pub fn save_user_config(&self, user_config: &UserConfig) {
         let config_path = Path::new(&self.config_path);
 
-        // 读取现有配置
+        // 读取现有配置，只替换 user 部分，保留 plugin 元数据
         let mut config = match self.load_config() {
             Ok(config) => config,
             Err(_) => {
@@ -85,21 +253,7 @@ impl ConfigManager {
             }
         };
 
-        // 更新用户配置
-        let user_config = UserConfig {
-            api_key: if api_key.trim().is_empty() {
-                None
-            } else {
-                Some(api_key.to_string())
-            },
-            api_url: if api_url.trim().is_empty() {
-                None
-            } else {
-                Some(api_url.to_string())
-            },
-        };
-
-        config.user = Some(user_config);
+        config.user = Some(user_config.clone());
 
         // 保存到文件
         match toml::to_string_pretty(&config) {
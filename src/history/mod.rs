@@ -0,0 +1,3 @@
+mod processor;
+
+pub use processor::HistoryProcessor;
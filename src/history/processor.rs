@@ -23,25 +23,11 @@ impl HistoryProcessor {
         // 转换为 AI 消息格式
         for history_msg in completed_messages {
             if !history_msg.content.trim().is_empty() {
-                // 根据角色转换消息
-                let ai_role = match history_msg.role.as_str() {
-                    "user" => "user",
-                    "plugin" => "assistant", // 插件回复作为助手回复
-                    "system" => "system",
-                    _ => {
-                        log_warn!(
-                            "Unknown role '{}' in history message, treating as user",
-                            history_msg.role
-                        );
-                        "user"
-                    }
-                };
-
-                messages.push(Message::new(ai_role, &history_msg.content));
+                messages.push(to_message(history_msg));
 
                 log_info!(
                     "Added message: role={}, content_length={}",
-                    ai_role,
+                    messages.last().unwrap().role,
                     history_msg.content.len()
                 );
             }
@@ -79,26 +65,206 @@ impl HistoryProcessor {
         );
 
         // 转换为 AI 消息格式
-        let mut messages = Vec::new();
-        for history_msg in recent_messages {
-            if !history_msg.content.trim().is_empty() {
-                let ai_role = match history_msg.role.as_str() {
-                    "user" => "user",
-                    "plugin" => "assistant",
-                    "system" => "system",
-                    _ => {
-                        log_warn!(
-                            "Unknown role '{}' in history message, treating as user",
-                            history_msg.role
-                        );
-                        "user"
-                    }
-                };
-
-                messages.push(Message::new(ai_role, &history_msg.content));
+        recent_messages
+            .into_iter()
+            .filter(|msg| !msg.content.trim().is_empty())
+            .map(to_message)
+            .collect()
+    }
+
+    /// 在给定 token 预算内，从历史记录中提取尽量多的已完成消息
+    ///
+    /// 按时间倒序（最新优先）累加每条消息的估算 token 数，直到再加入下一条
+    /// 会超过 `budget - reserve_for_reply` 为止，然后恢复时间正序。最前面的
+    /// system 消息即使因此被裁掉，也会被强制保留，避免系统提示丢失。
+    pub fn extract_within_token_budget(
+        history: Vec<HistoryMessage>,
+        budget: usize,
+        reserve_for_reply: usize,
+    ) -> Vec<Message> {
+        let completed: Vec<&HistoryMessage> = history
+            .iter()
+            .filter(|msg| msg.status == "completed" && !msg.content.trim().is_empty())
+            .collect();
+
+        let has_leading_system = completed.first().map(|m| m.role == "system").unwrap_or(false);
+        let available = budget.saturating_sub(reserve_for_reply);
+
+        let mut used_tokens = 0usize;
+        let mut kept_indices: Vec<usize> = Vec::new();
+
+        for (idx, msg) in completed.iter().enumerate().rev() {
+            let cost = estimate_tokens(&msg.content);
+            if used_tokens + cost > available && !kept_indices.is_empty() {
+                break;
             }
+            used_tokens += cost;
+            kept_indices.push(idx);
         }
 
-        messages
+        kept_indices.reverse();
+
+        if has_leading_system && kept_indices.first() != Some(&0) {
+            kept_indices.insert(0, 0);
+        }
+
+        log_info!(
+            "Token budget {} (reserve {} for reply): kept {} of {} completed messages (~{} tokens)",
+            budget,
+            reserve_for_reply,
+            kept_indices.len(),
+            completed.len(),
+            used_tokens
+        );
+
+        kept_indices
+            .into_iter()
+            .map(|idx| to_message(completed[idx]))
+            .collect()
+    }
+}
+
+/// 将历史消息转换为发往 AI 的消息格式，未知角色按 user 处理
+fn to_message(history_msg: &HistoryMessage) -> Message {
+    let ai_role = match history_msg.role.as_str() {
+        "user" => "user",
+        "plugin" => "assistant", // 插件回复作为助手回复
+        "assistant" => "assistant",
+        "system" => "system",
+        _ => {
+            log_warn!(
+                "Unknown role '{}' in history message, treating as user",
+                history_msg.role
+            );
+            "user"
+        }
+    };
+
+    Message::new(ai_role, &history_msg.content)
+}
+
+/// 粗略估算一段文本的 token 数
+///
+/// 没有接入真实分词器，按经验法则近似：CJK 字符信息密度高，按 1 字符 ≈ 1
+/// token 计算；其余文本（主要是拉丁字母和标点）按约 4 字符 ≈ 1 token 计算，
+/// 与大多数 BPE 分词器对英文文本的平均压缩比接近。
+fn estimate_tokens(text: &str) -> usize {
+    let mut cjk_chars = 0usize;
+    let mut other_chars = 0usize;
+
+    for ch in text.chars() {
+        if ch.is_whitespace() {
+            continue;
+        }
+        if is_cjk(ch) {
+            cjk_chars += 1;
+        } else {
+            other_chars += 1;
+        }
+    }
+
+    cjk_chars + (other_chars + 3) / 4
+}
+
+/// 判断一个字符是否落在常见 CJK 字符区间（中日韩统一表意文字、假名、谚文）
+fn is_cjk(ch: char) -> bool {
+    matches!(ch as u32,
+        0x4E00..=0x9FFF
+            | 0x3400..=0x4DBF
+            | 0x3040..=0x30FF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completed(role: &str, content: &str, created_at: i64) -> HistoryMessage {
+        HistoryMessage {
+            id: created_at.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            status: "completed".to_string(),
+            created_at,
+        }
+    }
+
+    #[test]
+    fn keeps_newest_messages_first_when_trimming() {
+        // 每条消息约 1 token（拉丁字母 "aaaa" => (4+3)/4 = 1），预算只够留下
+        // 最新的两条，最旧的一条应该被裁掉
+        let history = vec![
+            completed("user", "aaaa", 1),
+            completed("assistant", "bbbb", 2),
+            completed("user", "cccc", 3),
+        ];
+
+        let kept = HistoryProcessor::extract_within_token_budget(history, 2, 0);
+
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].content, "bbbb");
+        assert_eq!(kept[1].content, "cccc");
+        // 仍按时间正序返回（最旧的保留消息在前）
+    }
+
+    #[test]
+    fn respects_budget_minus_reserve_boundary() {
+        // 两条消息各约 1 token；budget - reserve == 2 时刚好两条都放得下，
+        // 降到 1 时只放得下最新的一条
+        let history = vec![completed("user", "aaaa", 1), completed("user", "bbbb", 2)];
+
+        let both_fit = HistoryProcessor::extract_within_token_budget(history.clone(), 2, 0);
+        assert_eq!(both_fit.len(), 2);
+
+        let only_newest_fits = HistoryProcessor::extract_within_token_budget(history, 2, 1);
+        assert_eq!(only_newest_fits.len(), 1);
+        assert_eq!(only_newest_fits[0].content, "bbbb");
+    }
+
+    #[test]
+    fn always_keeps_at_least_the_newest_message_even_over_budget() {
+        // 即使预算为 0，第一条（也是唯一一条）被选中的消息也不会因为超预算
+        // 而被排除——kept_indices 为空时不会提前 break
+        let history = vec![completed("user", "aaaa", 1)];
+
+        let kept = HistoryProcessor::extract_within_token_budget(history, 0, 0);
+
+        assert_eq!(kept.len(), 1);
+    }
+
+    #[test]
+    fn force_keeps_leading_system_message_even_when_trimmed_out() {
+        // 预算只够留下最新的一条非 system 消息，但最前面的 system 消息
+        // 应该被强制保留
+        let history = vec![
+            completed("system", "aaaa", 1),
+            completed("user", "aaaa", 2),
+            completed("assistant", "aaaa", 3),
+        ];
+
+        let kept = HistoryProcessor::extract_within_token_budget(history, 1, 0);
+
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].role, "system");
+        assert_eq!(kept[1].role, "assistant");
+    }
+
+    #[test]
+    fn ignores_non_completed_and_empty_messages() {
+        let mut pending = completed("user", "aaaa", 1);
+        pending.status = "pending".to_string();
+
+        let history = vec![
+            pending,
+            completed("user", "   ", 2),
+            completed("user", "aaaa", 3),
+        ];
+
+        let kept = HistoryProcessor::extract_within_token_budget(history, 100, 0);
+
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].content, "aaaa");
     }
 }
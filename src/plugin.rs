@@ -4,20 +4,76 @@ use plugin_interfaces::{
     PluginHandler, PluginInstanceContext, StreamError,
 };
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::runtime::Runtime;
+use tokio::sync::{Mutex, Notify};
 
-use crate::api::{ApiClient, Message};
-use crate::config::ConfigManager;
+use crate::api::{create_provider, ApiClient, DeltaKind, GenOptions, Message};
+use crate::config::{
+    BackendMode, BotProfile, ConfigManager, ProviderKind, RolePreset, SidecarConfig, UserConfig,
+};
 use crate::history::HistoryProcessor;
+use crate::server::GatewayServer;
+use crate::sidecar::SidecarProcess;
+
+/// sidecar 启动后等待它在健康检查端点上就绪的最长时间
+const SIDECAR_READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 为当前回复预留的 token 余量，历史消息截断时不会挤占这部分预算
+const RESERVE_TOKENS_FOR_REPLY: usize = 1024;
+
+/// 把 UI 文本框内容转换为持久化用的 `Option<String>`，空白视为未设置
+fn non_empty(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
 
 /// DeepSeek 对话插件
 #[derive(Clone)]
 pub struct DeepSeekPlugin {
     runtime: Option<Arc<Runtime>>,
 
-    // 配置
+    // 所有已保存的机器人档案，以及当前激活的档案名称
+    profiles: Vec<BotProfile>,
+    active_profile: String,
+
+    // 当前激活档案的可编辑字段（切换 active_profile 时从 profiles 中加载）
+    provider: String,
     api_key: String,
     api_url: String,
+    model: String,
+    temperature: String,
+    max_tokens: String,
+
+    // 跨档案共享的设置
+    top_p: String,
+    /// 逗号分隔的停止序列，解析后作为 `GenOptions.stop` 传给供应商
+    stop_sequences: String,
+    roles: Vec<RolePreset>,
+    active_role: String,
+    proxy: String,
+    request_timeout_secs: String,
+    connect_timeout_secs: String,
+
+    // 本地 OpenAI 兼容网关：填了端口就在下一次 update_config 时启动
+    gateway_port: String,
+    gateway_started: bool,
+
+    // Arena 模式：填了逗号分隔的档案名就会并发向这些档案各发一次请求
+    arena_profiles: String,
+
+    // 本地推理 sidecar：backend 为 local_sidecar 时用这些字段启动子进程
+    backend_mode: String,
+    sidecar_binary: String,
+    sidecar_model_path: String,
+    sidecar_port: String,
+    sidecar_extra_args: String,
+    sidecar_started: bool,
+    sidecar: Arc<Mutex<Option<SidecarProcess>>>,
 
     // 组件
     api_client: Option<ApiClient>,
@@ -26,29 +82,198 @@ pub struct DeepSeekPlugin {
 
 impl DeepSeekPlugin {
     pub fn new() -> Self {
-        Self {
+        let default_profile = BotProfile::new("default");
+        let mut plugin = Self {
             runtime: None,
+            profiles: vec![default_profile.clone()],
+            active_profile: default_profile.name.clone(),
+            provider: ProviderKind::DeepSeek.as_str().to_string(),
             api_key: String::new(),
-            api_url: "https://api.deepseek.com/v1/chat/completions".to_string(),
+            api_url: String::new(),
+            model: String::new(),
+            temperature: String::new(),
+            max_tokens: String::new(),
+            top_p: String::new(),
+            stop_sequences: String::new(),
+            roles: Vec::new(),
+            active_role: String::new(),
+            proxy: String::new(),
+            request_timeout_secs: String::new(),
+            connect_timeout_secs: String::new(),
+            gateway_port: String::new(),
+            gateway_started: false,
+            arena_profiles: String::new(),
+            backend_mode: BackendMode::RemoteApi.as_str().to_string(),
+            sidecar_binary: String::new(),
+            sidecar_model_path: String::new(),
+            sidecar_port: String::new(),
+            sidecar_extra_args: String::new(),
+            sidecar_started: false,
+            sidecar: Arc::new(Mutex::new(None)),
             api_client: None,
             config_manager: ConfigManager::new("user.toml"),
+        };
+        plugin.load_profile_fields(&default_profile);
+        plugin
+    }
+
+    /// 把一个档案的字段载入当前可编辑的 UI 字段
+    fn load_profile_fields(&mut self, profile: &BotProfile) {
+        self.provider = profile.provider.as_str().to_string();
+        self.api_key = profile.api_key.clone().unwrap_or_default();
+        self.api_url = profile.api_url.clone().unwrap_or_default();
+        self.model = profile.model.clone().unwrap_or_default();
+        self.temperature = profile
+            .temperature
+            .map(|v| v.to_string())
+            .unwrap_or_default();
+        self.max_tokens = profile
+            .max_tokens
+            .map(|v| v.to_string())
+            .unwrap_or_default();
+
+        self.backend_mode = profile.backend.as_str().to_string();
+        match &profile.sidecar {
+            Some(sidecar) => {
+                self.sidecar_binary = sidecar.binary_path.clone();
+                self.sidecar_model_path = sidecar.model_path.clone();
+                self.sidecar_port = sidecar.port.to_string();
+                self.sidecar_extra_args = sidecar.extra_args.clone();
+            }
+            None => {
+                self.sidecar_binary.clear();
+                self.sidecar_model_path.clear();
+                self.sidecar_port.clear();
+                self.sidecar_extra_args.clear();
+            }
+        }
+    }
+
+    /// 把当前 sidecar 相关的 UI 字段拼成一个 `SidecarConfig`，没填可执行文件就视为未配置
+    fn sidecar_config_from_fields(&self) -> Option<SidecarConfig> {
+        if self.sidecar_binary.trim().is_empty() {
+            return None;
         }
+        Some(SidecarConfig {
+            binary_path: self.sidecar_binary.trim().to_string(),
+            model_path: self.sidecar_model_path.trim().to_string(),
+            port: self.sidecar_port.trim().parse().unwrap_or(0),
+            extra_args: self.sidecar_extra_args.trim().to_string(),
+        })
+    }
+
+    /// 把当前可编辑字段存入（新增或替换）`profiles` 中同名的档案
+    fn upsert_active_profile(&mut self) {
+        // sidecar 就绪后 self.api_url 是内存里临时改写的回环地址：档案沿用原来
+        // 保存的地址；backend 切回 remote 时再把原地址恢复到编辑框
+        let mut api_url = non_empty(&self.api_url);
+        if self.sidecar_started {
+            if let Some(saved) = self
+                .profiles
+                .iter()
+                .find(|p| p.name == self.active_profile && p.backend == BackendMode::LocalSidecar)
+            {
+                api_url = saved.api_url.clone();
+                if BackendMode::parse(&self.backend_mode) != BackendMode::LocalSidecar {
+                    self.api_url = api_url.clone().unwrap_or_default();
+                }
+            }
+        }
+
+        let profile = BotProfile {
+            name: self.active_profile.clone(),
+            provider: ProviderKind::parse(&self.provider),
+            api_key: non_empty(&self.api_key),
+            api_url,
+            model: non_empty(&self.model),
+            temperature: self.temperature.trim().parse().ok(),
+            max_tokens: self.max_tokens.trim().parse().ok(),
+            backend: BackendMode::parse(&self.backend_mode),
+            sidecar: self.sidecar_config_from_fields(),
+        };
+
+        match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    /// 切换激活的档案：存在则载入其字段，不存在则视为新建
+    fn switch_active_profile(&mut self) {
+        match self
+            .profiles
+            .iter()
+            .find(|p| p.name == self.active_profile)
+            .cloned()
+        {
+            Some(profile) => {
+                log_info!("Switched to existing profile '{}'", self.active_profile);
+                self.load_profile_fields(&profile);
+            }
+            None => {
+                log_info!("Creating new profile '{}'", self.active_profile);
+            }
+        }
+        self.update_config();
     }
 
     /// 更新配置并初始化客户端
     fn update_config(&mut self) {
+        self.upsert_active_profile();
+
         // 保存用户配置到文件
-        self.config_manager
-            .save_user_config(&self.api_key, &self.api_url);
+        let user_config = UserConfig {
+            profiles: self.profiles.clone(),
+            active_profile: non_empty(&self.active_profile),
+            top_p: self.top_p.trim().parse().ok(),
+            stop_sequences: non_empty(&self.stop_sequences),
+            roles: self.roles.clone(),
+            active_role: non_empty(&self.active_role),
+            proxy: non_empty(&self.proxy),
+            request_timeout_secs: self.request_timeout_secs.trim().parse().ok(),
+            connect_timeout_secs: self.connect_timeout_secs.trim().parse().ok(),
+            gateway_port: self.gateway_port.trim().parse().ok(),
+            arena_profiles: non_empty(&self.arena_profiles),
+        };
+        self.config_manager.save_user_config(&user_config);
+
+        self.refresh_api_client();
+
+        // 启动本地网关（只在填了端口且尚未启动过时执行一次；更换端口
+        // 需要重新加载插件，因为监听的 TCP socket 一旦绑定就不会再释放）
+        if !self.gateway_started {
+            if let Ok(port) = self.gateway_port.trim().parse::<u16>() {
+                if let (Some(runtime), Some(api_client)) = (&self.runtime, &self.api_client) {
+                    GatewayServer::new(port).spawn(runtime, api_client.clone());
+                    self.gateway_started = true;
+                    log_info!("Local OpenAI-compatible gateway starting on port {}", port);
+                }
+            }
+        }
+    }
 
-        // 初始化API客户端
-        self.api_client = Some(ApiClient::new(self.api_key.clone(), self.api_url.clone()));
+    /// 根据当前内存中的 provider/api_key/api_url/model 重建 API 客户端并异步初始化
+    ///
+    /// 只影响内存状态：`update_config` 在持久化配置之后调用它，
+    /// `ensure_sidecar_started` 把 `api_url` 临时指向本地回环地址之后也调用它，
+    /// 但两处都不会把这份内存态写回 `profiles`/`user.toml`。
+    fn refresh_api_client(&mut self) {
+        let provider_kind = ProviderKind::parse(&self.provider);
+        let provider = create_provider(
+            &provider_kind,
+            self.api_key.clone(),
+            self.api_url.clone(),
+            self.model.clone(),
+        );
+        self.api_client = Some(ApiClient::new(provider));
 
-        // 初始化HTTP客户端
+        let proxy = non_empty(&self.proxy);
+        let timeout_secs = self.request_timeout_secs.trim().parse().ok();
+        let connect_timeout_secs = self.connect_timeout_secs.trim().parse().ok();
         if let (Some(runtime), Some(api_client)) = (&self.runtime, &self.api_client) {
             let client = api_client.clone();
             runtime.spawn(async move {
-                client.initialize().await;
+                client.initialize(proxy, timeout_secs, connect_timeout_secs).await;
             });
         }
     }
@@ -57,76 +282,300 @@ impl DeepSeekPlugin {
     fn load_user_config(&mut self) {
         let user_config = self.config_manager.load_user_config();
 
-        if let Some(api_key) = user_config.api_key {
-            self.api_key = api_key;
-            log_info!("Loaded API key from config");
+        self.profiles = user_config.profiles;
+        if self.profiles.is_empty() {
+            self.profiles.push(BotProfile::new("default"));
+        }
+
+        self.active_profile = user_config
+            .active_profile
+            .unwrap_or_else(|| self.profiles[0].name.clone());
+
+        if let Some(profile) = self
+            .profiles
+            .iter()
+            .find(|p| p.name == self.active_profile)
+            .cloned()
+        {
+            log_info!("Loaded profile '{}' from config", self.active_profile);
+            self.load_profile_fields(&profile);
+        }
+
+        if let Some(top_p) = user_config.top_p {
+            self.top_p = top_p.to_string();
+        }
+        if let Some(stop_sequences) = user_config.stop_sequences {
+            self.stop_sequences = stop_sequences;
+        }
+        self.roles = user_config.roles;
+        if let Some(active_role) = user_config.active_role {
+            self.active_role = active_role;
+        }
+        if let Some(proxy) = user_config.proxy {
+            self.proxy = proxy;
+        }
+        if let Some(request_timeout_secs) = user_config.request_timeout_secs {
+            self.request_timeout_secs = request_timeout_secs.to_string();
+        }
+        if let Some(connect_timeout_secs) = user_config.connect_timeout_secs {
+            self.connect_timeout_secs = connect_timeout_secs.to_string();
+        }
+        if let Some(gateway_port) = user_config.gateway_port {
+            self.gateway_port = gateway_port.to_string();
+        }
+        if let Some(arena_profiles) = user_config.arena_profiles {
+            self.arena_profiles = arena_profiles;
+        }
+    }
+
+    /// 生成参数：把 UI 文本框里的字符串解析为 `GenOptions`
+    fn gen_options(&self) -> GenOptions {
+        GenOptions {
+            stream: true,
+            temperature: self.temperature.trim().parse().ok(),
+            max_tokens: self.max_tokens.trim().parse().ok(),
+            top_p: self.top_p.trim().parse().ok(),
+            stop: self.stop_sequences_vec(),
         }
-        if let Some(api_url) = user_config.api_url {
-            self.api_url = api_url;
-            log_info!("Loaded API URL from config");
+    }
+
+    /// 把逗号分隔的 `stop_sequences` 文本框解析为 `Vec<String>`，全空则不设置
+    fn stop_sequences_vec(&self) -> Option<Vec<String>> {
+        let sequences: Vec<String> = self
+            .stop_sequences
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if sequences.is_empty() {
+            None
+        } else {
+            Some(sequences)
+        }
+    }
+
+    /// 当前选中角色预设对应的系统提示词
+    fn active_system_prompt(&self) -> Option<String> {
+        let active_role = self.active_role.trim();
+        if active_role.is_empty() {
+            return None;
         }
+        self.roles
+            .iter()
+            .find(|role| role.name == active_role)
+            .map(|role| role.system_prompt.clone())
     }
 
-    /// 发送流式请求到 DeepSeek API
+    /// 发送流式请求到指定档案对应的 AI 服务
+    ///
+    /// Arena 模式下每个被选中的档案都各自调用一次本方法；`cancel` 是这一批
+    /// 请求共享的取消信号。调用方（`handle_message`）在某个任务遇到不可恢复
+    /// 的错误时会调用 `cancel.notify_waiters()`，此时本方法会提前返回，不再
+    /// 干等还没出结果的模型。用户取消某一路流（`StreamCancelled`）时本方法
+    /// 返回 `Ok`，调用方不会 notify，其它任务照常进行；只有本方法返回错误时
+    /// 调用方才会 notify，让同批任务一起提前结束。
     async fn send_streaming_request(
         self: Arc<Self>,
+        profile: &BotProfile,
         message: String,
         plugin_ctx: &PluginInstanceContext,
+        cancel: Arc<Notify>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if self.api_key.trim().is_empty() {
-            return Err("API Key 未设置".into());
+        let api_key = profile.api_key.clone().unwrap_or_default();
+        if api_key.trim().is_empty() && profile.backend != BackendMode::LocalSidecar {
+            return Err(format!("档案 '{}' 未设置 API Key", profile.name).into());
         }
 
-        let api_client = self.api_client.as_ref().ok_or("API 客户端未初始化")?;
+        // 每个档案各自拥有一套供应商/地址/模型，单独构建一个 API 客户端
+        let provider = create_provider(
+            &profile.provider,
+            api_key,
+            profile.api_url.clone().unwrap_or_default(),
+            profile.model.clone().unwrap_or_default(),
+        );
+        let api_client = ApiClient::new(provider);
+        api_client
+            .initialize(
+                non_empty(&self.proxy),
+                self.request_timeout_secs.trim().parse().ok(),
+                self.connect_timeout_secs.trim().parse().ok(),
+            )
+            .await;
 
         // 构建消息列表
         let mut messages = Vec::new();
 
-        // 处理历史消息
+        // 选中的角色预设作为系统提示词，排在所有历史消息之前
+        if let Some(system_prompt) = self.active_system_prompt() {
+            messages.push(Message::system(&system_prompt));
+        }
+
+        // 处理历史消息：按供应商默认上下文窗口留出回复预留量后做 token 预算截断
         if let Some(history_vec) = plugin_ctx.get_history() {
-            let historical_messages =
-                HistoryProcessor::extract_completed_messages(history_vec.clone());
+            let token_budget = profile.provider.default_token_budget();
+            let historical_messages = HistoryProcessor::extract_within_token_budget(
+                history_vec.clone(),
+                token_budget,
+                RESERVE_TOKENS_FOR_REPLY,
+            );
             messages.extend(historical_messages);
-            log_info!("Loaded {} completed historical messages", messages.len());
+            log_info!(
+                "[{}] Loaded {} historical messages within a {} token budget",
+                profile.name,
+                messages.len(),
+                token_budget
+            );
         } else {
-            log_info!("No history available");
+            log_info!("[{}] No history available", profile.name);
         }
 
         // 添加当前用户消息
         messages.push(Message::user(&message));
 
         log_info!(
-            "Sending {} total messages to AI (including current message)",
+            "[{}] Sending {} total messages to AI (including current message)",
+            profile.name,
             messages.len()
         );
 
-        // 发送请求
+        // 以共享的生成参数为基础，档案自己设置的 temperature/max_tokens 优先
+        let mut opts = self.gen_options();
+        if let Some(temperature) = profile.temperature {
+            opts.temperature = Some(temperature);
+        }
+        if let Some(max_tokens) = profile.max_tokens {
+            opts.max_tokens = Some(max_tokens);
+        }
+
+        // 发送请求；stream_id 带上档案名，宿主可以据此把多个模型的回复并排渲染
+        let model_tag = profile.name.clone();
         let self_clone1 = self.clone();
         let self_clone2 = self.clone();
         let self_clone3 = self.clone();
 
-        api_client
-            .send_streaming_request(
-                messages,
-                plugin_ctx,
-                move |ctx| self_clone1.send_message_stream_start(ctx),
-                move |stream_id, content, is_final, ctx| {
-                    self_clone2.send_message_stream(stream_id, content, is_final, ctx)
-                },
-                move |stream_id, success, error_msg, ctx| {
-                    self_clone3.send_message_stream_end(stream_id, success, error_msg, ctx)
-                },
-            )
-            .await
+        let request = api_client.send_streaming_request(
+            messages,
+            &opts,
+            plugin_ctx,
+            move |ctx| self_clone1.send_message_stream_start(&model_tag, ctx),
+            move |stream_id, content, kind, is_final, ctx| {
+                self_clone2.send_message_stream(stream_id, content, kind, is_final, ctx)
+            },
+            move |stream_id, success, error_msg, ctx| {
+                self_clone3.send_message_stream_end(stream_id, success, error_msg, ctx)
+            },
+        );
+
+        tokio::select! {
+            result = request => result,
+            _ = cancel.notified() => {
+                log_info!("[{}] Arena batch cancelled, stopping this model's stream", profile.name);
+                Ok(())
+            }
+        }
     }
 
-    /// 开始流式消息传输
+    /// 当前激活档案：已保存过的就直接用，否则由编辑框里的字段现拼一个
+    ///
+    /// 如果 sidecar 已经就绪，把内存里临时改写的本地回环地址叠加到返回值
+    /// 上——`self.profiles`/`user.toml` 里保存的仍然是用户配置的原始地址。
+    fn current_profile(&self) -> BotProfile {
+        let mut profile = self
+            .profiles
+            .iter()
+            .find(|p| p.name == self.active_profile)
+            .cloned()
+            .unwrap_or_else(|| BotProfile {
+                name: self.active_profile.clone(),
+                provider: ProviderKind::parse(&self.provider),
+                api_key: non_empty(&self.api_key),
+                api_url: non_empty(&self.api_url),
+                model: non_empty(&self.model),
+                temperature: self.temperature.trim().parse().ok(),
+                max_tokens: self.max_tokens.trim().parse().ok(),
+                backend: BackendMode::parse(&self.backend_mode),
+                sidecar: self.sidecar_config_from_fields(),
+            });
+
+        if self.sidecar_started && profile.backend == BackendMode::LocalSidecar {
+            profile.api_url = Some(self.api_url.clone());
+        }
+
+        profile
+    }
+
+    /// 如果当前激活档案把 backend 设为本地 sidecar，拉起对应的子进程并
+    /// 把 `api_url` 改写为它暴露出的本地接入点；只在本次挂载期间执行一次
+    fn ensure_sidecar_started(&mut self) {
+        if self.sidecar_started {
+            return;
+        }
+
+        let profile = self.current_profile();
+        if profile.backend != BackendMode::LocalSidecar {
+            return;
+        }
+
+        let Some(sidecar_config) = profile.sidecar.clone() else {
+            log_warn!(
+                "Profile '{}' uses local sidecar backend but has no sidecar config",
+                profile.name
+            );
+            return;
+        };
+
+        let Some(runtime) = self.runtime.clone() else {
+            log_warn!("Tokio runtime not initialized, cannot start sidecar");
+            return;
+        };
+
+        let result = runtime.block_on(SidecarProcess::spawn_and_wait_ready(
+            &sidecar_config,
+            SIDECAR_READY_TIMEOUT,
+        ));
+
+        match result {
+            Ok(sidecar) => {
+                // 只改写内存里的 api_url，不经过 update_config/upsert_active_profile，
+                // 避免把这个回环地址写回档案和 user.toml——用户以后把 backend 切回
+                // remote 时，原来配置的地址应该还在
+                self.api_url = SidecarProcess::local_api_url(sidecar_config.port);
+                let sidecar_slot = self.sidecar.clone();
+                runtime.block_on(async move {
+                    *sidecar_slot.lock().await = Some(sidecar);
+                });
+                self.sidecar_started = true;
+                log_info!(
+                    "Local sidecar for profile '{}' is ready, api_url switched to {} (in-memory only)",
+                    profile.name,
+                    self.api_url
+                );
+                self.refresh_api_client();
+            }
+            Err(e) => {
+                log_warn!("Failed to start local sidecar: {}", e);
+            }
+        }
+    }
+
+    /// Arena 模式下参与对比的档案：从 `arena_profiles` 里按逗号分隔的名称挑选
+    fn resolve_arena_profiles(&self) -> Vec<BotProfile> {
+        self.arena_profiles
+            .split(',')
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| self.profiles.iter().find(|p| p.name == name).cloned())
+            .collect()
+    }
+
+    /// 开始流式消息传输；`tag` 是本次请求对应的档案名
     fn send_message_stream_start(
         &self,
+        tag: &str,
         _plugin_ctx: &PluginInstanceContext,
     ) -> Result<String, Box<dyn std::error::Error>> {
-        // 简化实现，返回一个固定的流ID
-        Ok("stream_001".to_string())
+        // 简化实现，用档案名拼出一个可区分的流 ID
+        Ok(format!("stream_{}", tag))
     }
 
     /// 发送流式消息块
@@ -134,6 +583,7 @@ impl DeepSeekPlugin {
         &self,
         _stream_id: &str,
         _content: &str,
+        _kind: DeltaKind,
         _is_final: bool,
         _plugin_ctx: &PluginInstanceContext,
     ) -> Result<(), StreamError> {
@@ -158,6 +608,34 @@ impl PluginHandler for DeepSeekPlugin {
     fn update_ui(&mut self, _ctx: &Context, ui: &mut Ui, _plugin_ctx: &PluginInstanceContext) {
         ui.label("DeepSeek AI 配置");
 
+        // 档案选择：输入已有档案名称切换过去，输入新名称则立即用当前字段新建并保存
+        ui.horizontal(|ui| {
+            ui.label("Profile:");
+            if ui.text_edit_singleline(&mut self.active_profile).changed() {
+                self.switch_active_profile();
+            }
+        });
+
+        // 供应商选择（deepseek / openai_compatible / anthropic）
+        ui.horizontal(|ui| {
+            ui.label("Provider:");
+            let provider_response = ui.text_edit_singleline(&mut self.provider);
+            if provider_response.changed() {
+                log_info!("Provider updated to {}", self.provider);
+                self.update_config();
+            }
+        });
+
+        // 模型名称输入
+        ui.horizontal(|ui| {
+            ui.label("Model:");
+            let model_response = ui.text_edit_singleline(&mut self.model);
+            if model_response.changed() {
+                log_info!("Model updated");
+                self.update_config();
+            }
+        });
+
         // API Key 输入
         ui.horizontal(|ui| {
             ui.label("API Key:");
@@ -178,6 +656,124 @@ impl PluginHandler for DeepSeekPlugin {
             }
         });
 
+        // 代理与超时
+        ui.horizontal(|ui| {
+            ui.label("Proxy:");
+            if ui.text_edit_singleline(&mut self.proxy).changed() {
+                log_info!("Proxy updated");
+                self.update_config();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Request Timeout (s):");
+            if ui.text_edit_singleline(&mut self.request_timeout_secs).changed() {
+                self.update_config();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Connect Timeout (s):");
+            if ui.text_edit_singleline(&mut self.connect_timeout_secs).changed() {
+                self.update_config();
+            }
+        });
+
+        // 本地网关：填端口即可把插件暴露成 POST /v1/chat/completions
+        ui.horizontal(|ui| {
+            ui.label("Gateway Port:");
+            if ui.text_edit_singleline(&mut self.gateway_port).changed() {
+                self.update_config();
+            }
+        });
+        if self.gateway_started {
+            ui.label(format!(
+                "网关已启动: http://127.0.0.1:{}/v1/chat/completions",
+                self.gateway_port.trim()
+            ));
+        }
+
+        // 本地 sidecar 后端：backend 填 local_sidecar 时，下面几个字段用来启动子进程
+        ui.horizontal(|ui| {
+            ui.label("Backend:");
+            if ui.text_edit_singleline(&mut self.backend_mode).changed() {
+                self.update_config();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Sidecar Binary:");
+            if ui.text_edit_singleline(&mut self.sidecar_binary).changed() {
+                self.update_config();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Sidecar Model Path:");
+            if ui.text_edit_singleline(&mut self.sidecar_model_path).changed() {
+                self.update_config();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Sidecar Port:");
+            if ui.text_edit_singleline(&mut self.sidecar_port).changed() {
+                self.update_config();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Sidecar Extra Args:");
+            if ui.text_edit_singleline(&mut self.sidecar_extra_args).changed() {
+                self.update_config();
+            }
+        });
+        if self.sidecar_started {
+            ui.label(format!("Sidecar 已就绪，监听端口 {}", self.sidecar_port.trim()));
+        }
+
+        // Arena 模式：填几个逗号分隔的档案名，下次发消息会并发请求这些档案
+        ui.horizontal(|ui| {
+            ui.label("Arena Profiles:");
+            if ui.text_edit_singleline(&mut self.arena_profiles).changed() {
+                self.update_config();
+            }
+        });
+
+        // 生成参数
+        ui.horizontal(|ui| {
+            ui.label("Temperature:");
+            if ui.text_edit_singleline(&mut self.temperature).changed() {
+                self.update_config();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Max Tokens:");
+            if ui.text_edit_singleline(&mut self.max_tokens).changed() {
+                self.update_config();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Top P:");
+            if ui.text_edit_singleline(&mut self.top_p).changed() {
+                self.update_config();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Stop Sequences:");
+            if ui.text_edit_singleline(&mut self.stop_sequences).changed() {
+                self.update_config();
+            }
+        });
+
+        // 角色预设：按名称选择 roles 列表中配置好的系统提示词
+        ui.horizontal(|ui| {
+            ui.label("Role:");
+            if ui.text_edit_singleline(&mut self.active_role).changed() {
+                log_info!("Active role updated to {}", self.active_role);
+                self.update_config();
+            }
+        });
+        if let Some(system_prompt) = self.active_system_prompt() {
+            ui.label(format!("System Prompt: {}", system_prompt));
+        } else if !self.active_role.trim().is_empty() {
+            ui.label("状态: 未在 roles 中找到该角色，未应用系统提示词");
+        }
+
         // 状态显示
         if self.api_key.trim().is_empty() || self.api_url.trim().is_empty() {
             ui.label("状态: 请设置 API Key 和 URL");
@@ -209,6 +805,7 @@ impl PluginHandler for DeepSeekPlugin {
                 self.runtime = Some(Arc::new(runtime));
                 log_info!("Tokio runtime initialized successfully");
                 self.update_config();
+                self.ensure_sidecar_started();
             }
             Err(e) => {
                 log_warn!("Failed to initialize tokio runtime: {}", e);
@@ -231,8 +828,15 @@ impl PluginHandler for DeepSeekPlugin {
             metadata.instance_id.clone().unwrap_or("None".to_string())
         );
 
-        // 关闭 tokio 异步运行时
+        // 关闭 tokio 异步运行时（先回收 sidecar 子进程，避免留下孤儿进程）
         if let Some(runtime) = self.runtime.clone() {
+            let sidecar = self.sidecar.clone();
+            runtime.block_on(async move {
+                if let Some(sidecar) = sidecar.lock().await.take() {
+                    sidecar.shutdown().await;
+                }
+            });
+
             match Arc::try_unwrap(runtime) {
                 Ok(runtime) => {
                     runtime.shutdown_timeout(std::time::Duration::from_millis(10));
@@ -261,8 +865,9 @@ impl PluginHandler for DeepSeekPlugin {
             metadata.instance_id.clone().unwrap_or("None".to_string())
         );
 
-        // 校验是否配置了 api 和 key
-        if self.api_key.trim().is_empty() || self.api_url.trim().is_empty() {
+        // 本地 sidecar 后端不需要云端 API Key，只要求 URL 已就绪
+        let requires_api_key = self.current_profile().backend != BackendMode::LocalSidecar;
+        if (requires_api_key && self.api_key.trim().is_empty()) || self.api_url.trim().is_empty() {
             log_warn!("API Key not configured, please set in plugin settings");
             return Err("API Key not configured".into());
         }
@@ -299,19 +904,23 @@ impl PluginHandler for DeepSeekPlugin {
             metadata.instance_id.clone().unwrap_or("None".to_string())
         );
 
-        if self.api_key.trim().is_empty() {
-            return Err("请先在插件配置中设置 API Key".into());
-        }
+        let runtime = self.runtime.as_ref().ok_or("运行时未初始化")?;
+        let arena_profiles = self.resolve_arena_profiles();
 
-        // 启动异步任务处理流式请求
-        if let Some(runtime) = &self.runtime {
+        if arena_profiles.is_empty() {
+            // 单模型路径：只向当前激活的档案发一次请求
+            let profile = self.current_profile();
+            if self.api_key.trim().is_empty() && profile.backend != BackendMode::LocalSidecar {
+                return Err("请先在插件配置中设置 API Key".into());
+            }
             let self_arc = Arc::new(self.clone());
             let message_clone = message.to_string();
             let context_clone = plugin_ctx.clone();
+            let cancel = Arc::new(Notify::new());
 
             runtime.spawn(async move {
                 if let Err(e) = self_arc
-                    .send_streaming_request(message_clone, &context_clone)
+                    .send_streaming_request(&profile, message_clone, &context_clone, cancel)
                     .await
                 {
                     log_error!("Failed to send streaming request: {}", e);
@@ -320,7 +929,35 @@ impl PluginHandler for DeepSeekPlugin {
 
             Ok("正在处理您的请求...".to_string())
         } else {
-            Err("运行时未初始化".into())
+            // Arena 模式：同一条消息并发发给每个选中的档案，各自拥有独立的
+            // stream_id 和 tokio 任务；某个任务出错时会通知同批其它任务提前结束
+            let batch_cancel = Arc::new(Notify::new());
+            let count = arena_profiles.len();
+
+            for profile in arena_profiles {
+                let self_arc = Arc::new(self.clone());
+                let message_clone = message.to_string();
+                let context_clone = plugin_ctx.clone();
+                let cancel = batch_cancel.clone();
+                let notify_siblings = batch_cancel.clone();
+
+                runtime.spawn(async move {
+                    let profile_name = profile.name.clone();
+                    if let Err(e) = self_arc
+                        .send_streaming_request(&profile, message_clone, &context_clone, cancel)
+                        .await
+                    {
+                        log_error!(
+                            "Arena task for profile '{}' failed: {}, cancelling the rest of the batch",
+                            profile_name,
+                            e
+                        );
+                        notify_siblings.notify_waiters();
+                    }
+                });
+            }
+
+            Ok(format!("正在并发请求 {} 个模型...", count))
         }
     }
 }
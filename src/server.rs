@@ -0,0 +1,199 @@
+//! 内嵌的 OpenAI 兼容网关
+//!
+//! 把插件自身持有的 [`ApiClient`]（以及它背后的 api key、上游地址）
+//! 暴露成一个本地 `POST /v1/chat/completions` 端点，让编辑器、CLI 等
+//! 任意支持 OpenAI 协议的工具都可以直接把请求指向这个地址，由插件代为
+//! 转发给真正的上游供应商并把 SSE 流透传回去。
+//!
+//! 这条路径刻意不依赖 `PluginInstanceContext`：驱动它的是
+//! [`ApiClient::send_streaming_request_raw`]，与 `handle_message` 用到的
+//! `send_streaming_request` 共享同一套重试/SSE 解析逻辑，只是用普通回调
+//! 替换掉插件宿主的流式协议。
+
+use hyper::body::Bytes;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use plugin_interfaces::{log_error, log_info, log_warn};
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+use crate::api::{ApiClient, DeltaKind, GenOptions, Message};
+
+#[derive(Deserialize)]
+struct IncomingMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionsRequest {
+    #[serde(default)]
+    messages: Vec<IncomingMessage>,
+    #[serde(default)]
+    temperature: Option<f32>,
+    #[serde(default)]
+    max_tokens: Option<u32>,
+    #[serde(default)]
+    top_p: Option<f32>,
+    /// 是否以 SSE 分片返回；默认为 true，与 OpenAI 的默认行为一致
+    #[serde(default = "default_stream")]
+    stream: bool,
+}
+
+fn default_stream() -> bool {
+    true
+}
+
+/// 一个只服务单个端点的本地网关，生命周期绑定到插件的 tokio `Runtime`
+pub struct GatewayServer {
+    addr: SocketAddr,
+}
+
+impl GatewayServer {
+    pub fn new(port: u16) -> Self {
+        Self {
+            addr: SocketAddr::from(([127, 0, 0, 1], port)),
+        }
+    }
+
+    /// 在给定的 runtime 上后台启动监听；调用后立即返回，不阻塞调用方
+    pub fn spawn(&self, runtime: &Runtime, api_client: ApiClient) {
+        let addr = self.addr;
+        let api_client = Arc::new(api_client);
+
+        runtime.spawn(async move {
+            let make_svc = make_service_fn(move |_conn| {
+                let api_client = api_client.clone();
+                async move { Ok::<_, Infallible>(service_fn(move |req| handle(req, api_client.clone()))) }
+            });
+
+            log_info!("Local gateway listening on http://{}", addr);
+            if let Err(e) = Server::bind(&addr).serve(make_svc).await {
+                log_error!("Local gateway server error: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle(
+    req: Request<Body>,
+    api_client: Arc<ApiClient>,
+) -> Result<Response<Body>, Infallible> {
+    if req.method() != Method::POST || req.uri().path() != "/v1/chat/completions" {
+        return Ok(json_response(
+            StatusCode::NOT_FOUND,
+            &json!({ "error": "not found, try POST /v1/chat/completions" }),
+        ));
+    }
+
+    let body_bytes = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return Ok(json_response(
+                StatusCode::BAD_REQUEST,
+                &json!({ "error": format!("failed to read request body: {}", e) }),
+            ))
+        }
+    };
+
+    let parsed: ChatCompletionsRequest = match serde_json::from_slice(&body_bytes) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return Ok(json_response(
+                StatusCode::BAD_REQUEST,
+                &json!({ "error": format!("invalid request body: {}", e) }),
+            ))
+        }
+    };
+
+    let messages: Vec<Message> = parsed
+        .messages
+        .iter()
+        .map(|m| Message::new(&m.role, &m.content))
+        .collect();
+
+    let opts = GenOptions {
+        stream: true,
+        temperature: parsed.temperature,
+        max_tokens: parsed.max_tokens,
+        top_p: parsed.top_p,
+        stop: None,
+    };
+
+    if !parsed.stream {
+        // 调用方显式要求非流式响应：内部仍用 SSE 跟上游通信（复用同一套
+        // 重试/解析逻辑），但攒齐完整回答后一次性返回，不透传分片
+        let mut content = String::new();
+        let result = api_client
+            .send_streaming_request_raw(messages, &opts, |text, kind| {
+                if kind == DeltaKind::Answer {
+                    content.push_str(text);
+                }
+                Ok(())
+            })
+            .await;
+
+        return Ok(match result {
+            Ok(_) => json_response(
+                StatusCode::OK,
+                &json!({ "choices": [{ "message": { "role": "assistant", "content": content } }] }),
+            ),
+            Err(e) => json_response(StatusCode::BAD_GATEWAY, &json!({ "error": e.to_string() })),
+        });
+    }
+
+    let (mut sender, body) = Body::channel();
+
+    // 回调是同步的，不能等 body 通道腾出空间：分片先进无界队列，再由单独的
+    // 任务按客户端的读取速度转发；客户端断开后队列接收端随之关闭
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Bytes>();
+    tokio::spawn(async move {
+        while let Some(line) = rx.recv().await {
+            if sender.send_data(line).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let result = api_client
+            .send_streaming_request_raw(messages, &opts, |text, kind| {
+                // 对外统一按 OpenAI 的 choices[0].delta 形状返回，
+                // reasoning_content 字段沿用 deepseek-reasoner 的命名习惯
+                let field = match kind {
+                    DeltaKind::Answer => "content",
+                    DeltaKind::Reasoning => "reasoning_content",
+                };
+                let chunk = json!({ "choices": [{ "delta": { field: text } }] });
+                let line = Bytes::from(format!("data: {}\n\n", chunk));
+                tx.send(line).map_err(|_| "gateway client disconnected".into())
+            })
+            .await;
+
+        if let Err(e) = result {
+            log_warn!("Gateway stream ended with error: {}", e);
+        }
+
+        // 无论上游是否显式发过 [DONE]，都要补发终止哨兵，否则客户端只会
+        // 看到连接静默关闭
+        let _ = tx.send(Bytes::from("data: [DONE]\n\n"));
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/event-stream")
+        .body(body)
+        .unwrap())
+}
+
+fn json_response(status: StatusCode, body: &serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
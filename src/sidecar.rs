@@ -0,0 +1,101 @@
+//! 托管的本地推理 sidecar 进程
+//!
+//! 档案把 backend 设为 [`crate::config::BackendMode::LocalSidecar`] 时，
+//! 插件不再调用远程 API，而是自己拉起一个本地的 OpenAI 兼容推理服务
+//! （比如 llama.cpp server、Ollama），等它在健康检查端点上就绪后，把
+//! 插件内存里当前使用的 `api_url` 改写为本地地址（档案里保存的地址不变），
+//! `send_streaming_request` 的其余逻辑完全不用变。
+
+use plugin_interfaces::{log_info, log_warn};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tokio::time::Instant;
+
+use crate::config::SidecarConfig;
+
+/// 健康检查轮询的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// 一个已启动、已确认就绪的本地推理子进程
+pub struct SidecarProcess {
+    child: Child,
+    port: u16,
+}
+
+impl SidecarProcess {
+    /// 按配置启动子进程，轮询健康检查端点直到就绪或超时
+    pub async fn spawn_and_wait_ready(
+        config: &SidecarConfig,
+        ready_timeout: Duration,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut command = Command::new(&config.binary_path);
+        command
+            .arg("--model")
+            .arg(&config.model_path)
+            .arg("--port")
+            .arg(config.port.to_string())
+            .args(config.extra_args.split_whitespace())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        log_info!(
+            "Starting local sidecar '{}' with model '{}' on port {}",
+            config.binary_path,
+            config.model_path,
+            config.port
+        );
+        let child = command.spawn()?;
+
+        let sidecar = Self {
+            child,
+            port: config.port,
+        };
+        sidecar.wait_until_ready(ready_timeout).await?;
+        Ok(sidecar)
+    }
+
+    /// 轮询 `/health`，直到返回 2xx 或超时
+    async fn wait_until_ready(
+        &self,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let health_url = format!("http://127.0.0.1:{}/health", self.port);
+        let deadline = Instant::now() + timeout;
+        let client = reqwest::Client::new();
+
+        loop {
+            match client.get(&health_url).send().await {
+                Ok(response) if response.status().is_success() => {
+                    log_info!("Sidecar on port {} is ready", self.port);
+                    return Ok(());
+                }
+                _ => {
+                    if Instant::now() >= deadline {
+                        return Err(format!(
+                            "sidecar 在 {:?} 内未就绪 ({})",
+                            timeout, health_url
+                        )
+                        .into());
+                    }
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    /// sidecar 就绪后暴露出的本地 OpenAI 兼容接入点
+    pub fn local_api_url(port: u16) -> String {
+        format!("http://127.0.0.1:{}/v1/chat/completions", port)
+    }
+
+    /// 杀掉子进程并回收，避免留下孤儿进程
+    pub async fn shutdown(mut self) {
+        if let Err(e) = self.child.start_kill() {
+            log_warn!("Failed to kill sidecar process: {}", e);
+            return;
+        }
+        let _ = self.child.wait().await;
+    }
+}